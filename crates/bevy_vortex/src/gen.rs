@@ -0,0 +1,447 @@
+use bevy::{
+    prelude::*,
+    reflect::{ReflectComponent, TypeRegistry},
+    utils::hashbrown::HashMap,
+};
+use std::fmt::Write as _;
+
+use crate::operator::{Operator, OperatorInputs, ReflectOperator};
+
+/// An expression produced by a single [`Operator`] node in the graph.
+///
+/// `gen()` on an `Operator` returns one of these describing how to compute that operator's
+/// output in terms of WGSL. The [`CodeGen`] pass walks the graph and lowers a tree of `Expr`
+/// into flat, CSE'd `let` bindings.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    /// A constant color literal.
+    ConstColor(LinearRgba),
+    /// A constant scalar literal.
+    ConstFloat(f32),
+    /// Reference to a named shader binding (uniform, texture, etc.) that the caller must supply a
+    /// value for. [`CodeGen::generate`] lists its name in [`GeneratedShader::bindings`]; it does
+    /// not write a declaration for it into the generated source.
+    Binding(String),
+    /// Reference to an already-emitted `let vN` binding, by variable name. Built by
+    /// [`CodeGen::resolve_inputs`] for each connected slot of an [`OperatorInputs`] and handed to
+    /// `Operator::gen()`, which folds it into whatever position in its own expression that input
+    /// belongs at.
+    Var(String),
+    /// Call a WGSL function (built-in or imported) with the given argument expressions.
+    Call(String, Vec<Expr>),
+}
+
+/// An import directive resolved against a [`SnippetRegistry`]: the WGSL source for a reusable
+/// helper function, plus the list of `#import` paths that snippet itself depends on.
+#[derive(Debug, Clone, Default)]
+pub struct Snippet {
+    pub source: String,
+    pub imports: Vec<String>,
+}
+
+/// Registry of reusable WGSL snippets keyed by import path, modeled on lyra's wgsl-preprocessor.
+/// A snippet's dependencies are the paths in its [`Snippet::imports`]; snippet sources are not
+/// scanned for `#import` lines.
+#[derive(Default)]
+pub struct SnippetRegistry {
+    snippets: HashMap<String, Snippet>,
+}
+
+impl SnippetRegistry {
+    pub fn insert(&mut self, path: impl Into<String>, snippet: Snippet) {
+        self.snippets.insert(path.into(), snippet);
+    }
+
+    /// Whether `path` names a registered snippet.
+    pub fn contains(&self, path: &str) -> bool {
+        self.snippets.contains_key(path)
+    }
+
+    /// Resolve `path` and all of its transitive `#import`s into a single block of WGSL source,
+    /// in dependency order, with each snippet emitted at most once and cycles rejected.
+    pub fn resolve(&self, path: &str) -> Result<String, PreprocessError> {
+        let mut out = String::new();
+        let mut emitted = std::collections::HashSet::new();
+        let mut visiting = std::collections::HashSet::new();
+        self.resolve_into(path, &mut out, &mut emitted, &mut visiting)?;
+        Ok(out)
+    }
+
+    fn resolve_into(
+        &self,
+        path: &str,
+        out: &mut String,
+        emitted: &mut std::collections::HashSet<String>,
+        visiting: &mut std::collections::HashSet<String>,
+    ) -> Result<(), PreprocessError> {
+        if emitted.contains(path) {
+            return Ok(());
+        }
+        if !visiting.insert(path.to_string()) {
+            return Err(PreprocessError::ImportCycle(path.to_string()));
+        }
+        let snippet = self
+            .snippets
+            .get(path)
+            .ok_or_else(|| PreprocessError::MissingImport(path.to_string()))?;
+        for import in &snippet.imports {
+            self.resolve_into(import, out, emitted, visiting)?;
+        }
+        out.push_str(&snippet.source);
+        out.push('\n');
+        emitted.insert(path.to_string());
+        visiting.remove(path);
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PreprocessError {
+    MissingImport(String),
+    ImportCycle(String),
+}
+
+/// Lowers a connected graph of [`Operator`]s into WGSL source, traversing backward from the
+/// `Output` node through each operator's [`OperatorInputs`] connections.
+///
+/// Each operator's `Expr` is emitted as an SSA-style `let vN = ...;` binding; structurally
+/// identical expressions are deduplicated so the same sub-expression is only computed once.
+#[derive(Default)]
+pub struct CodeGen {
+    bindings: Vec<(String, Expr)>,
+    cache: HashMap<Expr, String>,
+    next_var: usize,
+}
+
+/// A compiled fragment shader: WGSL source plus the bindings it requires.
+pub struct GeneratedShader {
+    pub source: String,
+    pub bindings: Vec<String>,
+}
+
+impl CodeGen {
+    /// Emit `expr` as a `let` binding, returning the variable name to reference it by. If an
+    /// identical expression has already been emitted, returns the existing binding instead of
+    /// emitting a duplicate.
+    pub fn emit(&mut self, expr: Expr) -> String {
+        if let Some(existing) = self.cache.get(&expr) {
+            return existing.clone();
+        }
+        let var = format!("v{}", self.next_var);
+        self.next_var += 1;
+        self.bindings.push((var.clone(), expr.clone()));
+        self.cache.insert(expr, var.clone());
+        var
+    }
+
+    fn expr_to_wgsl(&self, expr: &Expr) -> String {
+        match expr {
+            Expr::ConstColor(c) => {
+                format!("vec4<f32>({}, {}, {}, {})", c.red, c.green, c.blue, c.alpha)
+            }
+            Expr::ConstFloat(f) => format!("{f}"),
+            Expr::Binding(name) => name.clone(),
+            Expr::Var(name) => name.clone(),
+            Expr::Call(name, args) => {
+                let args = args
+                    .iter()
+                    .map(|a| self.expr_to_wgsl(a))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                format!("{name}({args})")
+            }
+        }
+    }
+
+    /// Traverse the graph starting at `output`, collecting every connected operator's `Expr` and
+    /// assembling a fragment entry point that writes the final color.
+    pub fn generate(
+        &mut self,
+        world: &World,
+        registry: &SnippetRegistry,
+        output: Entity,
+    ) -> Result<GeneratedShader, PreprocessError> {
+        let type_registry = world.resource::<AppTypeRegistry>().read();
+        let final_var = self.visit(world, &type_registry, output);
+
+        let mut imports = String::new();
+        let mut import_paths = std::collections::HashSet::new();
+        let mut bindings = Vec::new();
+        let mut binding_names = std::collections::HashSet::new();
+        for (_, expr) in &self.bindings {
+            for path in Self::referenced_imports(expr, registry) {
+                if import_paths.insert(path.clone()) {
+                    imports.push_str(&registry.resolve(&path)?);
+                }
+            }
+            Self::collect_bindings(expr, &mut binding_names, &mut bindings);
+        }
+
+        let mut source = String::new();
+        source.push_str(&imports);
+        source.push_str("\n@fragment\nfn fragment() -> @location(0) vec4<f32> {\n");
+        for (var, expr) in &self.bindings {
+            let _ = writeln!(source, "    let {var} = {};", self.expr_to_wgsl(expr));
+        }
+        let _ = writeln!(source, "    return {final_var};");
+        source.push('}');
+
+        Ok(GeneratedShader { source, bindings })
+    }
+
+    /// Collect the names of every [`Expr::Binding`] referenced anywhere within `expr`, in
+    /// first-encountered order with duplicates removed.
+    fn collect_bindings(
+        expr: &Expr,
+        seen: &mut std::collections::HashSet<String>,
+        out: &mut Vec<String>,
+    ) {
+        match expr {
+            Expr::Binding(name) => {
+                if seen.insert(name.clone()) {
+                    out.push(name.clone());
+                }
+            }
+            Expr::Call(_, args) => {
+                for arg in args {
+                    Self::collect_bindings(arg, seen, out);
+                }
+            }
+            Expr::ConstColor(_) | Expr::ConstFloat(_) | Expr::Var(_) => {}
+        }
+    }
+
+    fn visit(&mut self, world: &World, type_registry: &TypeRegistry, entity: Entity) -> String {
+        let inputs = self.resolve_inputs(world, type_registry, entity);
+        let expr = Self::get_operator(world, type_registry, entity)
+            .map(|op| op.gen(&inputs))
+            .unwrap_or(Expr::ConstColor(LinearRgba::BLACK));
+        self.emit(expr)
+    }
+
+    /// Visit every connected [`OperatorInputs`] slot on `entity`, keyed by field name, so
+    /// `Operator::gen` can fold each one into the right position of its own expression. A field
+    /// with no connection is simply absent from the map; `gen` falls back to that field's own
+    /// literal default, exactly as it already does for a fully-unconnected operator.
+    fn resolve_inputs(
+        &mut self,
+        world: &World,
+        type_registry: &TypeRegistry,
+        entity: Entity,
+    ) -> HashMap<String, Expr> {
+        let Some(inputs) = world.get::<OperatorInputs>(entity) else {
+            return HashMap::new();
+        };
+        let connections: Vec<(String, Entity)> = inputs
+            .0
+            .iter()
+            .filter_map(|input| {
+                input
+                    .connection
+                    .map(|upstream| (input.field.clone(), upstream))
+            })
+            .collect();
+        // The upstream operator has already been (or will be) visited in its own right; reference
+        // its binding rather than re-emitting its expression, so the cache in `emit` can
+        // recognize shared sub-expressions across sibling inputs.
+        connections
+            .into_iter()
+            .map(|(field, upstream)| {
+                let upstream_var = self.visit(world, type_registry, upstream);
+                (field, Expr::Var(upstream_var))
+            })
+            .collect()
+    }
+
+    /// Fetch `entity`'s [`Operator`] impl via reflection: find whichever of its components is
+    /// registered with both `ReflectComponent` and `ReflectOperator` data and return a trait
+    /// object for it. `World::get::<dyn Operator>(..)` isn't valid — `Component` requires
+    /// `Sized`, so a trait object can't be fetched directly — the type registry is what lets us
+    /// recover a `&dyn Operator` from whichever concrete operator struct is actually attached.
+    fn get_operator<'w>(
+        world: &'w World,
+        type_registry: &TypeRegistry,
+        entity: Entity,
+    ) -> Option<&'w dyn Operator> {
+        let entity_ref = world.get_entity(entity)?;
+        for component_id in entity_ref.archetype().components() {
+            let Some(registration) = world
+                .components()
+                .get_info(component_id)
+                .and_then(|info| info.type_id())
+                .and_then(|type_id| type_registry.get(type_id))
+            else {
+                continue;
+            };
+            let (Some(reflect_operator), Some(reflect_component)) = (
+                registration.data::<ReflectOperator>(),
+                registration.data::<ReflectComponent>(),
+            ) else {
+                continue;
+            };
+            if let Some(operator) = reflect_component
+                .reflect(entity_ref)
+                .and_then(|reflected| reflect_operator.get(reflected))
+            {
+                return Some(operator);
+            }
+        }
+        None
+    }
+
+    /// Collect the `#import`-style snippet paths `expr` depends on: any `Call(name, _)` whose
+    /// `name` is registered in `registry` is itself taken to be an import path.
+    fn referenced_imports(expr: &Expr, registry: &SnippetRegistry) -> Vec<String> {
+        match expr {
+            Expr::Call(name, args) => {
+                let mut paths = Vec::new();
+                if registry.contains(name) {
+                    paths.push(name.clone());
+                }
+                for arg in args {
+                    paths.extend(Self::referenced_imports(arg, registry));
+                }
+                paths
+            }
+            _ => Vec::new(),
+        }
+    }
+}
+
+impl std::hash::Hash for Expr {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        match self {
+            Expr::ConstColor(c) => {
+                0u8.hash(state);
+                c.red.to_bits().hash(state);
+                c.green.to_bits().hash(state);
+                c.blue.to_bits().hash(state);
+                c.alpha.to_bits().hash(state);
+            }
+            Expr::ConstFloat(f) => {
+                1u8.hash(state);
+                f.to_bits().hash(state);
+            }
+            Expr::Binding(name) => {
+                2u8.hash(state);
+                name.hash(state);
+            }
+            Expr::Var(name) => {
+                3u8.hash(state);
+                name.hash(state);
+            }
+            Expr::Call(name, args) => {
+                4u8.hash(state);
+                name.hash(state);
+                args.hash(state);
+            }
+        }
+    }
+}
+
+impl Eq for Expr {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn emit_deduplicates_identical_expressions() {
+        let mut gen = CodeGen::default();
+        let a = gen.emit(Expr::ConstFloat(1.0));
+        let b = gen.emit(Expr::ConstFloat(1.0));
+        let c = gen.emit(Expr::ConstFloat(2.0));
+
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+        assert_eq!(gen.bindings.len(), 2);
+    }
+
+    #[test]
+    fn collect_bindings_dedupes_across_call_args() {
+        let mut seen = std::collections::HashSet::new();
+        let mut out = Vec::new();
+        let expr = Expr::Call(
+            "mix".to_string(),
+            vec![
+                Expr::Binding("uTime".to_string()),
+                Expr::Binding("uTime".to_string()),
+                Expr::Binding("uColor".to_string()),
+            ],
+        );
+
+        CodeGen::collect_bindings(&expr, &mut seen, &mut out);
+
+        assert_eq!(out, vec!["uTime".to_string(), "uColor".to_string()]);
+    }
+
+    #[test]
+    fn referenced_imports_matches_calls_registered_in_the_registry() {
+        let mut registry = SnippetRegistry::default();
+        registry.insert("noise", Snippet::default());
+        let expr = Expr::Call(
+            "noise".to_string(),
+            vec![Expr::Call("unregistered".to_string(), vec![])],
+        );
+
+        let imports = CodeGen::referenced_imports(&expr, &registry);
+
+        assert_eq!(imports, vec!["noise".to_string()]);
+    }
+
+    #[test]
+    fn resolve_returns_imports_before_the_importing_snippet() {
+        let mut registry = SnippetRegistry::default();
+        registry.insert(
+            "a",
+            Snippet {
+                source: "fn a() {}\n".to_string(),
+                imports: vec!["b".to_string()],
+            },
+        );
+        registry.insert(
+            "b",
+            Snippet {
+                source: "fn b() {}\n".to_string(),
+                imports: vec![],
+            },
+        );
+
+        let resolved = registry.resolve("a").unwrap();
+
+        assert!(resolved.find("fn b()").unwrap() < resolved.find("fn a()").unwrap());
+    }
+
+    #[test]
+    fn resolve_detects_missing_import() {
+        let registry = SnippetRegistry::default();
+        assert_eq!(
+            registry.resolve("missing"),
+            Err(PreprocessError::MissingImport("missing".to_string()))
+        );
+    }
+
+    #[test]
+    fn resolve_detects_import_cycle() {
+        let mut registry = SnippetRegistry::default();
+        registry.insert(
+            "a",
+            Snippet {
+                source: String::new(),
+                imports: vec!["b".to_string()],
+            },
+        );
+        registry.insert(
+            "b",
+            Snippet {
+                source: String::new(),
+                imports: vec!["a".to_string()],
+            },
+        );
+
+        assert_eq!(
+            registry.resolve("a"),
+            Err(PreprocessError::ImportCycle("a".to_string()))
+        );
+    }
+}
@@ -1,4 +1,4 @@
-use bevy::prelude::*;
+use bevy::{prelude::*, utils::hashbrown::HashMap};
 
 use crate::{
     gen::Expr,
@@ -22,8 +22,12 @@ impl Operator for Output {
         Box::new(self.clone())
     }
 
-    fn gen(&self) -> Expr {
-        // todo!()
-        Expr::ConstColor(LinearRgba::WHITE)
+    fn gen(&self, inputs: &HashMap<String, Expr>) -> Expr {
+        // `CodeGen` hands us the upstream expression for `"input"` when it's connected; fall back
+        // to our own literal default otherwise.
+        inputs
+            .get("input")
+            .cloned()
+            .unwrap_or(Expr::ConstColor(self.input))
     }
 }
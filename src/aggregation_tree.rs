@@ -0,0 +1,392 @@
+use bevy::{
+    prelude::{Component, Entity, World},
+    utils::hashbrown::HashSet,
+};
+
+/// Fan-in factor for leaf-mode aggregate nodes: once a node has this many direct children, the
+/// next `attach` splits it into two bucket children instead of letting it keep growing, which is
+/// what bounds the tree's height to `O(log(N))` regardless of attach order (see
+/// [`AggregationTree::attach`]).
+const FANIN: usize = 8;
+
+/// A node in the aggregation tree, mirroring one entity in the view hierarchy. Summarizes
+/// whether this entity or any of its descendants currently have pending re-attach work, so that
+/// `reattach_children` can query a root without descending into unaffected subtrees.
+#[derive(Component, Default)]
+pub(crate) struct Aggregate {
+    /// Parent aggregate, if any. Root aggregates have no parent.
+    parent: Option<Entity>,
+    /// Total number of entities in this node's subtree, including itself. Used only to decide,
+    /// in router mode, which of the two child buckets is currently lighter — new attachments are
+    /// routed toward whichever has fewer descendants, which is what keeps the tree reasonably
+    /// balanced (depth `O(log N)`) instead of letting one lineage grow without bound.
+    count: usize,
+    /// This node's direct children. While `is_router` is `false` these are plain attached
+    /// entities (up to `FANIN` of them); `split` replaces them with exactly two bucket
+    /// aggregates, each inheriting half, once a `FANIN + 1`th child would otherwise be added.
+    children: Vec<Entity>,
+    /// Whether this node has been split into exactly two bucket children. A router node never
+    /// accepts a new direct child itself; every attachment is routed into whichever of its two
+    /// children currently has fewer descendants.
+    is_router: bool,
+    /// Number of entities (direct or nested) currently reporting dirty state.
+    dirty_count: usize,
+    /// Scope entities within this subtree that need re-attaching, deduplicated against
+    /// ancestors that already account for them. On a root aggregate, this is exactly the set of
+    /// dirty views in its subtree, letting `reattach_children` read it directly instead of
+    /// re-scanning the ECS for an `OutputChanged` marker.
+    dirty_scopes: HashSet<Entity>,
+}
+
+impl Aggregate {
+    /// An aggregate node with no attached descendants yet, representing itself.
+    pub(crate) fn new() -> Self {
+        Self {
+            count: 1,
+            ..Default::default()
+        }
+    }
+
+    /// Whether this subtree has any pending re-attach work.
+    pub(crate) fn is_dirty(&self) -> bool {
+        self.dirty_count > 0
+    }
+
+    /// Whether this is a real aggregate root — either a [`ViewRoot`](crate::view::ViewRoot) or,
+    /// per `register_aggregates`'s fallback, a view whose ancestor chain never reaches a
+    /// registered `Aggregate` (e.g. a non-view ancestor). Bucket nodes created by `attach`'s
+    /// splitting always have a parent, so they're never mistaken for roots.
+    pub(crate) fn is_root(&self) -> bool {
+        self.parent.is_none()
+    }
+}
+
+/// Internal subsystem consulted by `reattach_children` and `rebuild_views` in place of their
+/// previous full queries. Maintains, per view node, an aggregated summary of descendant dirty
+/// state so bottom-up invalidation is `O(log N)` and root queries are `O(1)`.
+#[derive(Default)]
+pub(crate) struct AggregationTree;
+
+impl AggregationTree {
+    /// Mark `scope_entity`'s own `Aggregate` node as dirty, then walk upward through ancestor
+    /// aggregates, incrementing each one's counters. Stops as soon as an ancestor already has
+    /// `scope_entity` recorded, since every ancestor above that point is already accounted for.
+    pub(crate) fn mark_dirty(world: &mut World, scope_entity: Entity) {
+        let mut current = Some(scope_entity);
+        let mut first = true;
+        while let Some(entity) = current {
+            let Some(mut aggregate) = world.get_mut::<Aggregate>(entity) else {
+                break;
+            };
+            if !first && aggregate.dirty_scopes.contains(&scope_entity) {
+                break;
+            }
+            first = false;
+            if aggregate.dirty_scopes.insert(scope_entity) {
+                aggregate.dirty_count += 1;
+            }
+            current = aggregate.parent;
+        }
+    }
+
+    /// Clear `scope_entity` from its own aggregate and every ancestor that recorded it, called
+    /// once the corresponding re-attach work has been performed.
+    pub(crate) fn clear_dirty(world: &mut World, scope_entity: Entity) {
+        let mut current = Some(scope_entity);
+        while let Some(entity) = current {
+            let Some(mut aggregate) = world.get_mut::<Aggregate>(entity) else {
+                break;
+            };
+            if aggregate.dirty_scopes.remove(&scope_entity) {
+                aggregate.dirty_count = aggregate.dirty_count.saturating_sub(1);
+            } else {
+                break;
+            }
+            current = aggregate.parent;
+        }
+    }
+
+    /// Query whether `root` (or any descendant bucketed beneath it) has pending re-attach work,
+    /// without descending into the subtree.
+    pub(crate) fn root_has_pending_work(world: &World, root: Entity) -> bool {
+        world
+            .get::<Aggregate>(root)
+            .map(Aggregate::is_dirty)
+            .unwrap_or(false)
+    }
+
+    /// Return the scope entities marked dirty anywhere within `root`'s subtree, without clearing
+    /// them. `mark_dirty` records every dirtied descendant on each ancestor it climbs through, so
+    /// a root aggregate's `dirty_scopes` is exactly this subtree's full work list — letting
+    /// callers read it directly in `O(dirty count)` instead of re-scanning the whole subtree.
+    pub(crate) fn dirty_scopes(world: &World, root: Entity) -> Vec<Entity> {
+        world
+            .get::<Aggregate>(root)
+            .map(|a| a.dirty_scopes.iter().copied().collect())
+            .unwrap_or_default()
+    }
+
+    /// Register `entity` under `parent`'s aggregate.
+    ///
+    /// While `parent` (or whichever bucket it routes into) has fewer than `FANIN` direct
+    /// children, `entity` attaches there directly. Once a node would exceed `FANIN`, it splits
+    /// into two bucket children (each inheriting half of its current children), and every future
+    /// attachment is routed toward whichever of the two currently has fewer descendants
+    /// (tracked via [`Aggregate::count`]). Splitting into two balanced subtrees rather than
+    /// chaining a single reused overflow bucket is what bounds the tree's height to
+    /// `O(log(N))` regardless of attach order, instead of degenerating into an
+    /// `O(N)` chain the way a single ever-growing bucket would.
+    pub(crate) fn attach(world: &mut World, entity: Entity, parent: Entity) {
+        let mut node = parent;
+        loop {
+            let (is_router, children) = {
+                let aggregate = world
+                    .get::<Aggregate>(node)
+                    .expect("attach parent must have an Aggregate");
+                (aggregate.is_router, aggregate.children.clone())
+            };
+            if !is_router {
+                if children.len() < FANIN {
+                    break;
+                }
+                Self::split(world, node);
+                continue;
+            }
+            let [a, b]: [Entity; 2] = children
+                .try_into()
+                .expect("a router aggregate always has exactly two children");
+            let a_count = world.get::<Aggregate>(a).unwrap().count;
+            let b_count = world.get::<Aggregate>(b).unwrap().count;
+            node = if a_count <= b_count { a } else { b };
+        }
+
+        world.entity_mut(entity).insert(Aggregate::new());
+        world
+            .get_mut::<Aggregate>(node)
+            .unwrap()
+            .children
+            .push(entity);
+        world.get_mut::<Aggregate>(entity).unwrap().parent = Some(node);
+        // Every ancestor up to the root, not only the nodes routed through above, gains one
+        // entity in its subtree.
+        let mut current = Some(node);
+        while let Some(ancestor) = current {
+            let mut aggregate = world.get_mut::<Aggregate>(ancestor).unwrap();
+            aggregate.count += 1;
+            current = aggregate.parent;
+        }
+    }
+
+    /// Replace `node`'s `FANIN` direct children with two new bucket aggregates, each inheriting
+    /// half, and mark `node` as a router. `node`'s own `count`/`dirty_count`/`dirty_scopes` are
+    /// left untouched: they already correctly summarize its subtree regardless of how its
+    /// children are internally organized.
+    fn split(world: &mut World, node: Entity) {
+        let children = std::mem::take(&mut world.get_mut::<Aggregate>(node).unwrap().children);
+        let mid = children.len() / 2;
+        let (lo, hi) = children.split_at(mid);
+        let bucket_lo = Self::spawn_bucket(world, node, lo);
+        let bucket_hi = Self::spawn_bucket(world, node, hi);
+        let mut aggregate = world.get_mut::<Aggregate>(node).unwrap();
+        aggregate.children = vec![bucket_lo, bucket_hi];
+        aggregate.is_router = true;
+    }
+
+    /// Spawn a bucket aggregate under `parent` holding `members` as its direct children,
+    /// inheriting each member's `count` and dirty state (a member's own `dirty_scopes` is
+    /// already the full transitive set for its subtree, so the union of all members' sets is
+    /// exactly the new bucket's), and re-parenting each member to point at the new bucket.
+    fn spawn_bucket(world: &mut World, parent: Entity, members: &[Entity]) -> Entity {
+        let count: usize = members
+            .iter()
+            .map(|m| world.get::<Aggregate>(*m).unwrap().count)
+            .sum();
+        let mut dirty_scopes = HashSet::new();
+        for &member in members {
+            dirty_scopes.extend(
+                world
+                    .get::<Aggregate>(member)
+                    .unwrap()
+                    .dirty_scopes
+                    .iter()
+                    .copied(),
+            );
+        }
+        let dirty_count = dirty_scopes.len();
+        let bucket = world
+            .spawn(Aggregate {
+                parent: Some(parent),
+                count,
+                children: members.to_vec(),
+                is_router: false,
+                dirty_count,
+                dirty_scopes,
+            })
+            .id();
+        for &member in members {
+            world.get_mut::<Aggregate>(member).unwrap().parent = Some(bucket);
+        }
+        bucket
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn depth(world: &World, mut entity: Entity) -> usize {
+        let mut depth = 0;
+        while let Some(parent) = world.get::<Aggregate>(entity).unwrap().parent {
+            entity = parent;
+            depth += 1;
+        }
+        depth
+    }
+
+    #[test]
+    fn mark_dirty_propagates_to_ancestors() {
+        let mut world = World::new();
+        let root = world.spawn(Aggregate::new()).id();
+        let child = world.spawn_empty().id();
+        AggregationTree::attach(&mut world, child, root);
+
+        AggregationTree::mark_dirty(&mut world, child);
+
+        assert!(world.get::<Aggregate>(child).unwrap().is_dirty());
+        assert!(world.get::<Aggregate>(root).unwrap().is_dirty());
+        assert!(AggregationTree::root_has_pending_work(&world, root));
+        assert_eq!(AggregationTree::dirty_scopes(&world, root), vec![child]);
+    }
+
+    #[test]
+    fn mark_dirty_is_idempotent_for_repeated_scopes() {
+        let mut world = World::new();
+        let root = world.spawn(Aggregate::new()).id();
+        let child = world.spawn_empty().id();
+        AggregationTree::attach(&mut world, child, root);
+
+        AggregationTree::mark_dirty(&mut world, child);
+        AggregationTree::mark_dirty(&mut world, child);
+
+        assert_eq!(world.get::<Aggregate>(root).unwrap().dirty_count, 1);
+    }
+
+    #[test]
+    fn clear_dirty_removes_scope_from_every_ancestor() {
+        let mut world = World::new();
+        let root = world.spawn(Aggregate::new()).id();
+        let child = world.spawn_empty().id();
+        AggregationTree::attach(&mut world, child, root);
+        AggregationTree::mark_dirty(&mut world, child);
+
+        AggregationTree::clear_dirty(&mut world, child);
+
+        assert!(!world.get::<Aggregate>(child).unwrap().is_dirty());
+        assert!(!world.get::<Aggregate>(root).unwrap().is_dirty());
+        assert!(!AggregationTree::root_has_pending_work(&world, root));
+        assert!(AggregationTree::dirty_scopes(&world, root).is_empty());
+    }
+
+    /// Dirty state recorded before a split must still be reachable from the new bucket that ends
+    /// up owning the dirty scope, so `clear_dirty` keeps working after the tree reshapes itself.
+    #[test]
+    fn dirty_state_survives_a_split() {
+        let mut world = World::new();
+        let root = world.spawn(Aggregate::new()).id();
+        let mut children = Vec::new();
+        for _ in 0..FANIN {
+            let child = world.spawn_empty().id();
+            AggregationTree::attach(&mut world, child, root);
+            children.push(child);
+        }
+        let dirty_child = children[0];
+        AggregationTree::mark_dirty(&mut world, dirty_child);
+
+        // This overflow attach triggers `root` splitting into two buckets.
+        let overflow = world.spawn_empty().id();
+        AggregationTree::attach(&mut world, overflow, root);
+        assert!(world.get::<Aggregate>(root).unwrap().is_router);
+
+        assert!(AggregationTree::root_has_pending_work(&world, root));
+        AggregationTree::clear_dirty(&mut world, dirty_child);
+        assert!(!AggregationTree::root_has_pending_work(&world, root));
+    }
+
+    #[test]
+    fn attach_splits_into_two_buckets_after_fanin_children() {
+        let mut world = World::new();
+        let root = world.spawn(Aggregate::new()).id();
+        for _ in 0..FANIN {
+            let child = world.spawn_empty().id();
+            AggregationTree::attach(&mut world, child, root);
+        }
+        assert!(!world.get::<Aggregate>(root).unwrap().is_router);
+
+        let overflow_child = world.spawn_empty().id();
+        AggregationTree::attach(&mut world, overflow_child, root);
+
+        let root_agg = world.get::<Aggregate>(root).unwrap();
+        assert!(root_agg.is_router);
+        assert_eq!(root_agg.children.len(), 2);
+        let bucket = world
+            .get::<Aggregate>(overflow_child)
+            .unwrap()
+            .parent
+            .unwrap();
+        assert_eq!(world.get::<Aggregate>(bucket).unwrap().parent, Some(root));
+    }
+
+    #[test]
+    fn new_attachments_route_to_the_lighter_bucket() {
+        let mut world = World::new();
+        let root = world.spawn(Aggregate::new()).id();
+        for _ in 0..(FANIN + 1) {
+            let child = world.spawn_empty().id();
+            AggregationTree::attach(&mut world, child, root);
+        }
+        // `root` is now a router with two buckets of FANIN/2 and FANIN/2 + 1 entities.
+        let [bucket_a, bucket_b]: [Entity; 2] = world
+            .get::<Aggregate>(root)
+            .unwrap()
+            .children
+            .clone()
+            .try_into()
+            .unwrap();
+        let lighter = if world.get::<Aggregate>(bucket_a).unwrap().count
+            <= world.get::<Aggregate>(bucket_b).unwrap().count
+        {
+            bucket_a
+        } else {
+            bucket_b
+        };
+
+        let next = world.spawn_empty().id();
+        AggregationTree::attach(&mut world, next, root);
+
+        assert_eq!(world.get::<Aggregate>(next).unwrap().parent, Some(lighter));
+    }
+
+    #[test]
+    fn long_attach_chains_stay_logarithmic_depth_instead_of_linear() {
+        let mut world = World::new();
+        let root = world.spawn(Aggregate::new()).id();
+
+        let mut last = None;
+        let total = FANIN * FANIN * FANIN;
+        for _ in 0..total {
+            let child = world.spawn_empty().id();
+            AggregationTree::attach(&mut world, child, root);
+            last = Some(child);
+        }
+
+        // With FANIN=8, 512 entities in a naive one-bucket-per-overflow chain would produce a
+        // depth of about total / FANIN = 64. A balanced binary split keeps depth within a small constant of
+        // log2(total / FANIN).
+        let max_expected_depth = (total / FANIN).max(1).ilog2() as usize + 2;
+        assert!(
+            depth(&world, last.unwrap()) <= max_expected_depth,
+            "expected depth <= {max_expected_depth}, got {}",
+            depth(&world, last.unwrap())
+        );
+    }
+}
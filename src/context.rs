@@ -0,0 +1,57 @@
+use crate::cx::Cx;
+use bevy::{
+    hierarchy::Parent,
+    prelude::{Component, Entity},
+    utils::hashbrown::HashMap,
+};
+use std::any::{Any, TypeId};
+
+/// Holds the typed values provided by a scope entity via [`Cx::use_provider`], keyed by
+/// `TypeId` so a single entity can provide several unrelated context types at once.
+#[derive(Component, Default)]
+pub(crate) struct ContextProviders(HashMap<TypeId, Box<dyn Any + Send + Sync>>);
+
+impl Cx {
+    /// Make `value` available to this view's descendants via [`Cx::use_context`], without
+    /// threading it through every intermediate `View`'s props. Values are keyed by type, so
+    /// providing a new `T` replaces any value of the same type provided earlier on this scope.
+    pub fn use_provider<T: Send + Sync + 'static>(&mut self, value: T) {
+        let scope_entity = self.owner();
+        let world = self.world_mut();
+        let mut providers = world
+            .entity_mut(scope_entity)
+            .take::<ContextProviders>()
+            .unwrap_or_default();
+        providers.0.insert(TypeId::of::<T>(), Box::new(value));
+        // Re-inserting the component (rather than mutating it in place through `get_mut`) is
+        // what fires the `OnInsert` hook `use_context`'s consumers watch for; mutating the
+        // existing component's inner map wouldn't trigger it, so updates after the first
+        // `use_provider` call on this scope would never wake a consumer's rebuild.
+        world.entity_mut(scope_entity).insert(providers);
+    }
+
+    /// Walk `Parent` links upward from this view's scope entity to find the nearest ancestor
+    /// that provided a `T` via [`Cx::use_provider`], recording a dependency on that ancestor so
+    /// this view rebuilds whenever the provided value changes. Returns `None` if no ancestor
+    /// provides a `T`.
+    pub fn use_context<T: Clone + Send + Sync + 'static>(&mut self) -> Option<T> {
+        let mut current = Some(self.owner());
+        while let Some(entity) = current {
+            // Read and clone the value (if any) before recording the dependency below, so the
+            // immutable borrow of `self.world()` doesn't overlap with the `&mut self` call.
+            let found = self
+                .world()
+                .entity(entity)
+                .get::<ContextProviders>()
+                .and_then(|providers| providers.0.get(&TypeId::of::<T>()))
+                .and_then(|value| value.downcast_ref::<T>())
+                .cloned();
+            if let Some(value) = found {
+                self.add_tracked_component::<ContextProviders>(entity);
+                return Some(value);
+            }
+            current = self.world().entity(entity).get::<Parent>().map(Parent::get);
+        }
+        None
+    }
+}
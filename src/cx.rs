@@ -0,0 +1,78 @@
+use crate::{reactive_observers::ObservedDependencies, tracking_scope::TrackingScope};
+use bevy::prelude::{Component, Entity, Resource, World};
+
+/// Build/rebuild-time context passed to [`View`](crate::view::View) methods. Gives access to the
+/// world, the entity that owns the current scope, and the scope's dependency tracking.
+///
+/// Holds its world/scope access as raw pointers rather than borrowed references so that `Cx`
+/// itself carries no lifetime parameters. This is what lets it be used in type positions (e.g.
+/// `ViewThunk<Cx>`) that require a `'static` context type.
+pub struct Cx {
+    world: *mut World,
+    owner: Entity,
+    scope: *mut TrackingScope,
+}
+
+impl Cx {
+    pub fn new(world: &mut World, owner: Entity, scope: &mut TrackingScope) -> Self {
+        Self {
+            world: world as *mut World,
+            owner,
+            scope: scope as *mut TrackingScope,
+        }
+    }
+
+    /// The scope entity that owns the view currently being built/rebuilt.
+    pub fn owner(&self) -> Entity {
+        self.owner
+    }
+
+    pub fn world(&self) -> &World {
+        // SAFETY: a `Cx` is only ever constructed for, and lives no longer than, a single
+        // build/rebuild call, which has exclusive access to `world` for that duration.
+        unsafe { &*self.world }
+    }
+
+    pub fn world_mut(&mut self) -> &mut World {
+        // SAFETY: see `world()`.
+        unsafe { &mut *self.world }
+    }
+
+    fn scope_mut(&mut self) -> &mut TrackingScope {
+        // SAFETY: see `world()`.
+        unsafe { &mut *self.scope }
+    }
+
+    /// Record a read of component `T` on `entity`. Installs an observer (once per `(entity, T)`
+    /// pair for this scope) so that an `OnInsert`/`OnRemove` of `T` on `entity` schedules this
+    /// view's scope for rebuild, rather than relying on a per-frame scan of every `TrackingScope`.
+    pub fn add_tracked_component<T: Component>(&mut self, entity: Entity) {
+        let scope_entity = self.owner;
+        let world = self.world_mut();
+        let component_id = world
+            .components()
+            .component_id::<T>()
+            .unwrap_or_else(|| world.register_component::<T>());
+
+        if world.get::<ObservedDependencies>(scope_entity).is_none() {
+            world
+                .entity_mut(scope_entity)
+                .insert(ObservedDependencies::default());
+        }
+        // `take` + reinsert lets us hand `watch` its own `&mut World` to spawn observers with,
+        // which `get_mut::<ObservedDependencies>(..)` alone wouldn't allow since it borrows world.
+        let mut observed = world
+            .entity_mut(scope_entity)
+            .take::<ObservedDependencies>()
+            .expect("just inserted above");
+        observed.watch(world, scope_entity, entity, component_id);
+        world.entity_mut(scope_entity).insert(observed);
+    }
+
+    /// Record a read of resource `T`. Resources aren't entities, so they can't be watched with
+    /// an entity observer the way component reads are above; instead they're rechecked on every
+    /// `rebuild_views` pass via `TrackingScope::resource_deps_changed`.
+    pub fn add_tracked_resource<T: Resource>(&mut self) {
+        self.scope_mut().track_resource::<T>();
+    }
+}
@@ -0,0 +1,127 @@
+use crate::view::{View, ViewContext};
+use bevy::prelude::World;
+
+/// State for a [`Memo`] view: the inner view's state, plus the last dependency value it was
+/// built or rebuilt with.
+pub struct MemoState<V: View<Ctx>, Ctx: ViewContext, D> {
+    inner: V::State,
+    dep: D,
+    marker: std::marker::PhantomData<fn() -> Ctx>,
+}
+
+/// A view that skips rebuilding its child when a dependency value hasn't changed.
+///
+/// `Memo` wraps a child view together with a dependency `D: PartialEq + Clone`. On `rebuild`,
+/// the new `D` is compared against the one stored from the last build; if they're equal,
+/// `rebuild` returns `false` immediately without invoking the child view's `rebuild` or touching
+/// `nodes()`. This mirrors Dioxus's `memoize(&other)` short-circuit on props, and gives callers a
+/// cheap, explicit escape hatch for expensive subtrees that only need to change when specific
+/// inputs do.
+pub struct Memo<V, D: PartialEq + Clone + Send + Sync + 'static> {
+    inner: V,
+    dep: D,
+}
+
+impl<V, D: PartialEq + Clone + Send + Sync + 'static> Memo<V, D> {
+    pub fn new(inner: V, dep: D) -> Self {
+        Self { inner, dep }
+    }
+}
+
+impl<V: View<Ctx>, Ctx: ViewContext, D: PartialEq + Clone + Send + Sync + 'static> View<Ctx>
+    for Memo<V, D>
+{
+    type State = MemoState<V, Ctx, D>;
+
+    fn nodes(&self, world: &World, state: &Self::State) -> crate::NodeSpan {
+        self.inner.nodes(world, &state.inner)
+    }
+
+    fn build(&self, cx: &mut Ctx) -> Self::State {
+        MemoState {
+            inner: self.inner.build(cx),
+            dep: self.dep.clone(),
+            marker: std::marker::PhantomData,
+        }
+    }
+
+    fn rebuild(&self, cx: &mut Ctx, state: &mut Self::State) -> bool {
+        if state.dep == self.dep {
+            return false;
+        }
+        state.dep = self.dep.clone();
+        self.inner.rebuild(cx, &mut state.inner)
+    }
+
+    fn attach_children(&self, world: &mut World, state: &mut Self::State) -> bool {
+        self.inner.attach_children(world, &mut state.inner)
+    }
+
+    fn raze(&self, world: &mut World, state: &mut Self::State) {
+        self.inner.raze(world, &mut state.inner);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{cx::Cx, tracking_scope::TrackingScope, NodeSpan};
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    struct CountingView(Arc<AtomicUsize>);
+
+    impl View<Cx> for CountingView {
+        type State = ();
+
+        fn nodes(&self, _world: &World, _state: &Self::State) -> NodeSpan {
+            NodeSpan::Empty
+        }
+
+        fn build(&self, _cx: &mut Cx) -> Self::State {}
+
+        fn rebuild(&self, _cx: &mut Cx, _state: &mut Self::State) -> bool {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            true
+        }
+
+        fn raze(&self, _world: &mut World, _state: &mut Self::State) {}
+    }
+
+    #[test]
+    fn skips_rebuild_when_dep_unchanged() {
+        let rebuild_count = Arc::new(AtomicUsize::new(0));
+        let mut world = World::new();
+        let owner = world.spawn_empty().id();
+        let mut scope = TrackingScope::new(world.change_tick());
+        let mut cx = Cx::new(&mut world, owner, &mut scope);
+
+        let view = Memo::new(CountingView(rebuild_count.clone()), 1);
+        let mut state = view.build(&mut cx);
+
+        let changed = view.rebuild(&mut cx, &mut state);
+
+        assert!(!changed);
+        assert_eq!(rebuild_count.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn rebuilds_when_dep_changed() {
+        let rebuild_count = Arc::new(AtomicUsize::new(0));
+        let mut world = World::new();
+        let owner = world.spawn_empty().id();
+        let mut scope = TrackingScope::new(world.change_tick());
+        let mut cx = Cx::new(&mut world, owner, &mut scope);
+
+        let view = Memo::new(CountingView(rebuild_count.clone()), 1);
+        let mut state = view.build(&mut cx);
+
+        let next = Memo::new(CountingView(rebuild_count.clone()), 2);
+        let changed = next.rebuild(&mut cx, &mut state);
+
+        assert!(changed);
+        assert_eq!(rebuild_count.load(Ordering::SeqCst), 1);
+    }
+}
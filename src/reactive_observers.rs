@@ -0,0 +1,82 @@
+use bevy::{
+    ecs::{
+        component::ComponentId,
+        observer::Observer,
+        world::{OnInsert, OnRemove},
+    },
+    prelude::{Component, Entity, Resource, World},
+    utils::hashbrown::{HashMap, HashSet},
+};
+
+/// Set of scope entities that need to be rebuilt on the next call to `rebuild_views`.
+///
+/// This is populated by observers installed via [`ObservedDependencies`] whenever a component
+/// that a [`TrackingScope`](crate::tracking_scope::TrackingScope) reads is inserted or removed,
+/// and drained once per frame, so component dependencies need no per-scope scan. Resource
+/// dependencies are not observed and never land here.
+#[derive(Resource, Default)]
+pub(crate) struct PendingRebuilds(pub(crate) HashSet<Entity>);
+
+impl PendingRebuilds {
+    pub(crate) fn drain(&mut self) -> Vec<Entity> {
+        self.0.drain().collect()
+    }
+}
+
+/// Tracks, for a single scope entity, which `(Entity, ComponentId)` observers it currently owns,
+/// so that re-subscribing on every rebuild doesn't leak duplicate observers.
+#[derive(Component, Default)]
+pub(crate) struct ObservedDependencies {
+    /// Keyed by the `(target entity, component)` pair being watched; the value is the pair of
+    /// observer entities (insert, remove) installed for it, so both can be despawned together.
+    watched: HashMap<(Entity, ComponentId), (Entity, Entity)>,
+}
+
+impl ObservedDependencies {
+    /// Ensure an observer is installed for `OnInsert`/`OnRemove` of `component_id` on `target`,
+    /// at most once per `(target, component_id)` pair so repeated reads in the same scope don't
+    /// spawn duplicate observers. When triggered, the observer pushes `scope_entity` into
+    /// the world's [`PendingRebuilds`] set.
+    pub(crate) fn watch(
+        &mut self,
+        world: &mut World,
+        scope_entity: Entity,
+        target: Entity,
+        component_id: ComponentId,
+    ) {
+        let key = (target, component_id);
+        if self.watched.contains_key(&key) {
+            return;
+        }
+
+        let observer_insert = Observer::new(
+            move |_trigger: bevy::prelude::Trigger<OnInsert>,
+                  mut pending: bevy::prelude::ResMut<PendingRebuilds>| {
+                pending.0.insert(scope_entity);
+            },
+        )
+        .with_component(component_id)
+        .with_entity(target);
+        let observer_remove = Observer::new(
+            move |_trigger: bevy::prelude::Trigger<OnRemove>,
+                  mut pending: bevy::prelude::ResMut<PendingRebuilds>| {
+                pending.0.insert(scope_entity);
+            },
+        )
+        .with_component(component_id)
+        .with_entity(target);
+
+        let insert_entity = world.spawn(observer_insert).id();
+        let remove_entity = world.spawn(observer_remove).id();
+        self.watched.insert(key, (insert_entity, remove_entity));
+    }
+
+    /// Despawn all observers owned by this scope. Called before a scope is rebuilt or razed so
+    /// that stale dependencies don't keep triggering rebuilds.
+    pub(crate) fn clear(&mut self, world: &mut World) {
+        for (_, (insert_entity, remove_entity)) in self.watched.drain() {
+            world.despawn(insert_entity);
+            world.despawn(remove_entity);
+        }
+    }
+}
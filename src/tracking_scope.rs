@@ -0,0 +1,54 @@
+use bevy::{
+    ecs::component::Tick,
+    prelude::{Component, Resource, World},
+};
+
+/// Per-scope bookkeeping for a reactive view.
+///
+/// Component reads are tracked via per-dependency observers (see
+/// [`reactive_observers::ObservedDependencies`](crate::reactive_observers::ObservedDependencies)),
+/// which push directly into `rebuild_views`'s pending set rather than being scanned here.
+/// Resources aren't entities, though, so they can't be watched the same way; those dependencies
+/// are recorded on the scope itself and rechecked each `rebuild_views` pass.
+#[derive(Component, Default)]
+pub struct TrackingScope {
+    pub(crate) tick: Tick,
+    pub(crate) cleanups: Vec<Box<dyn Fn(&mut World) + Send + Sync>>,
+    resource_deps: Vec<Box<dyn Fn(&World, Tick, Tick) -> bool + Send + Sync>>,
+}
+
+impl TrackingScope {
+    pub fn new(tick: Tick) -> Self {
+        Self {
+            tick,
+            cleanups: Vec::new(),
+            resource_deps: Vec::new(),
+        }
+    }
+
+    /// Record a read of resource `T`, checked on each `rebuild_views` pass against the tick this
+    /// scope was last rebuilt at.
+    pub(crate) fn track_resource<T: Resource>(&mut self) {
+        self.resource_deps
+            .push(Box::new(|world, scope_tick, this_run| {
+                world
+                    .get_resource_ref::<T>()
+                    .map(|r| r.last_changed().is_newer_than(scope_tick, this_run))
+                    .unwrap_or(false)
+            }));
+    }
+
+    /// Whether any resource tracked by this scope has changed since it was last rebuilt.
+    pub(crate) fn resource_deps_changed(&self, world: &World, this_run: Tick) -> bool {
+        self.resource_deps
+            .iter()
+            .any(|changed| changed(world, self.tick, this_run))
+    }
+
+    /// Replace this scope's resource dependencies and cleanups with `next`'s, called after a
+    /// rebuild has populated `next` with the dependencies read during that rebuild.
+    pub(crate) fn take_deps(&mut self, next: &mut TrackingScope) {
+        self.resource_deps = std::mem::take(&mut next.resource_deps);
+        self.cleanups = std::mem::take(&mut next.cleanups);
+    }
+}
@@ -0,0 +1,19 @@
+use crate::{cx::Cx, tracking_scope::TrackingScope, view::ViewContext};
+use bevy::prelude::{Entity, World};
+
+/// [`ViewContext`] implementation for the Bevy UI display-node backend. `Cx` is the context type
+/// views have always built/rebuilt against; this impl is what lets `View<Cx>` keep working
+/// unchanged now that [`View`](crate::view::View) is generic over its context.
+impl ViewContext for Cx {
+    fn new(world: &mut World, entity: Entity, scope: &mut TrackingScope) -> Self {
+        Cx::new(world, entity, scope)
+    }
+
+    fn world(&self) -> &World {
+        self.world()
+    }
+
+    fn world_mut(&mut self) -> &mut World {
+        self.world_mut()
+    }
+}
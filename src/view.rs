@@ -1,12 +1,36 @@
-use crate::{cx::Cx, tracking_scope::TrackingScope, NodeSpan};
+use crate::{
+    aggregation_tree::{Aggregate, AggregationTree},
+    cx::Cx,
+    reactive_observers::{ObservedDependencies, PendingRebuilds},
+    tracking_scope::TrackingScope,
+    NodeSpan,
+};
 use bevy::{
     hierarchy::Parent,
     log::info,
-    prelude::{Added, Component, Entity, With, World},
+    prelude::{Added, Component, Entity, With, Without, World},
     utils::hashbrown::HashSet,
 };
 use std::sync::{Arc, Mutex};
 
+/// The context a [`View`] builds and rebuilds against: constructed for the entity that owns the
+/// view, it gives access to the [`World`]. The trait itself has no spawning, parent-attachment or
+/// dependency-tracking methods; those go through that world access or through the concrete
+/// context type (e.g. [`Cx::add_tracked_component`]). [`Cx`] (via the [`ui_cx`](crate::ui_cx)
+/// module) is the implementation for the Bevy UI display-node backend; other domains (e.g. the
+/// shader-operator graph) can provide their own, letting the same `View` machinery target more
+/// than one kind of output.
+pub trait ViewContext: Sized + 'static {
+    /// Construct a new context for rebuilding the view owned by `entity`.
+    fn new(world: &mut World, entity: Entity, scope: &mut TrackingScope) -> Self;
+
+    /// Borrow the world this context is operating on.
+    fn world(&self) -> &World;
+
+    /// Mutably borrow the world this context is operating on.
+    fn world_mut(&mut self) -> &mut World;
+}
+
 #[allow(unused)]
 /// An object which produces one or more display nodes. The `View` is itself immutable and
 /// stateless, but it can produce a mutable state object which is updated when the view is rebuilt.
@@ -15,7 +39,11 @@ use std::sync::{Arc, Mutex};
 ///
 /// Views also produce outputs in the form of display nodes, which are entities in the ECS world.
 /// These can be Bevy UI elements, effects, or other entities that are part of the view hierarchy.
-pub trait View: Sync + Send + 'static {
+///
+/// `View` is generic over a [`ViewContext`] so the same reactive `build`/`rebuild`/`raze`
+/// machinery can target different output domains; most views only ever need `Ctx = Cx`, the UI
+/// display-node backend (see [`ui_cx`](crate::ui_cx)).
+pub trait View<Ctx: ViewContext = Cx>: Sync + Send + 'static {
     /// The external state for this View.
     type State: Send + Sync;
 
@@ -24,12 +52,12 @@ pub trait View: Sync + Send + 'static {
 
     /// Construct and patch the tree of UiNodes produced by this view.
     /// This may also spawn child entities representing nested components.
-    fn build(&self, cx: &mut Cx) -> Self::State;
+    fn build(&self, cx: &mut Ctx) -> Self::State;
 
     /// Update the internal state of this view, re-creating any UiNodes.
     /// Returns true if the output changed, that is, if `nodes()` would return a different value
     /// than it did before the rebuild.
-    fn rebuild(&self, cx: &mut Cx, state: &mut Self::State) -> bool;
+    fn rebuild(&self, cx: &mut Ctx, state: &mut Self::State) -> bool;
 
     /// Instructs the view to attach any child entities to their parent entity. This is called
     /// whenever we know that one or more child entities have changed their outputs. It also
@@ -49,7 +77,7 @@ pub trait View: Sync + Send + 'static {
     fn raze(&self, world: &mut World, state: &mut Self::State);
 
     // / Build a ViewRoot from this view.
-    fn to_root(self) -> (ViewStateCell<Self>, ViewThunk, ViewRoot)
+    fn to_root(self) -> (ViewStateCell<Self, Ctx>, ViewThunk<Ctx>, ViewRoot)
     where
         Self: Sized,
     {
@@ -65,13 +93,14 @@ pub trait View: Sync + Send + 'static {
 pub struct OutputChanged;
 
 /// Combination of a [`View`] and it's built state, stored as a trait object within a component.
-pub struct ViewState<V: View> {
+pub struct ViewState<V: View<Ctx>, Ctx: ViewContext> {
     pub(crate) view: V,
     pub(crate) state: Option<V::State>,
+    marker: std::marker::PhantomData<fn() -> Ctx>,
 }
 
-impl<V: View> ViewState<V> {
-    fn rebuild(&mut self, cx: &mut Cx) -> bool {
+impl<V: View<Ctx>, Ctx: ViewContext> ViewState<V, Ctx> {
+    fn rebuild(&mut self, cx: &mut Ctx) -> bool {
         if let Some(state) = self.state.as_mut() {
             self.view.rebuild(cx, state)
         } else {
@@ -97,26 +126,31 @@ impl<V: View> ViewState<V> {
 }
 
 #[derive(Component)]
-pub struct ViewStateCell<V: View>(pub Arc<Mutex<ViewState<V>>>);
+pub struct ViewStateCell<V: View<Ctx>, Ctx: ViewContext>(pub Arc<Mutex<ViewState<V, Ctx>>>);
 
-impl<V: View> ViewStateCell<V> {
+impl<V: View<Ctx>, Ctx: ViewContext> ViewStateCell<V, Ctx> {
     pub fn new(view: V) -> Self {
-        Self(Arc::new(Mutex::new(ViewState { view, state: None })))
+        Self(Arc::new(Mutex::new(ViewState {
+            view,
+            state: None,
+            marker: std::marker::PhantomData,
+        })))
     }
 
-    pub fn create_thunk(&self) -> ViewThunk {
-        ViewThunk(&ViewAdapter::<V> {
+    pub fn create_thunk(&self) -> ViewThunk<Ctx> {
+        ViewThunk(&ViewAdapter::<V, Ctx> {
             marker: std::marker::PhantomData,
         })
     }
 }
 
-pub struct ViewAdapter<V: View> {
-    marker: std::marker::PhantomData<V>,
+pub struct ViewAdapter<V: View<Ctx>, Ctx: ViewContext> {
+    marker: std::marker::PhantomData<(V, fn() -> Ctx)>,
 }
 
-/// Type-erased trait for a [`ViewState`].
-pub trait AnyViewAdapter: Sync + Send + 'static {
+/// Type-erased trait for a [`ViewState`], generic over the [`ViewContext`] its view builds
+/// against.
+pub trait AnyViewAdapter<Ctx: ViewContext>: Sync + Send + 'static {
     /// Return the span of entities produced by this View.
     fn nodes(&self, world: &mut World, entity: Entity) -> NodeSpan;
 
@@ -134,9 +168,9 @@ pub trait AnyViewAdapter: Sync + Send + 'static {
     fn attach_children(&self, world: &mut World, entity: Entity) -> bool;
 }
 
-impl<V: View> AnyViewAdapter for ViewAdapter<V> {
+impl<V: View<Ctx>, Ctx: ViewContext> AnyViewAdapter<Ctx> for ViewAdapter<V, Ctx> {
     fn nodes(&self, world: &mut World, entity: Entity) -> NodeSpan {
-        match world.entity(entity).get::<ViewStateCell<V>>() {
+        match world.entity(entity).get::<ViewStateCell<V, Ctx>>() {
             Some(view_cell) => {
                 let vstate = view_cell.0.lock().unwrap();
                 match &vstate.state {
@@ -149,11 +183,11 @@ impl<V: View> AnyViewAdapter for ViewAdapter<V> {
     }
 
     fn rebuild(&self, world: &mut World, entity: Entity, scope: &mut TrackingScope) -> bool {
-        let mut cx = Cx::new(world, entity, scope);
+        let mut cx = Ctx::new(world, entity, scope);
         if let Some(view_cell) = cx
             .world_mut()
             .entity_mut(entity)
-            .get_mut::<ViewStateCell<V>>()
+            .get_mut::<ViewStateCell<V, Ctx>>()
         {
             let inner = view_cell.0.clone();
             let mut vstate = inner.lock().unwrap();
@@ -164,13 +198,19 @@ impl<V: View> AnyViewAdapter for ViewAdapter<V> {
     }
 
     fn raze(&self, world: &mut World, entity: Entity) {
-        if let Some(vsh) = world.entity_mut(entity).take::<ViewStateCell<V>>() {
+        if let Some(vsh) = world.entity_mut(entity).take::<ViewStateCell<V, Ctx>>() {
             vsh.0.lock().unwrap().raze(world);
         }
+        // Observers spawned for this scope's tracked dependencies aren't children of `entity`
+        // (see `ObservedDependencies`), so despawning `entity` elsewhere would otherwise leave
+        // them orphaned, forever pushing a now-gone scope into `PendingRebuilds`.
+        if let Some(mut observed) = world.entity_mut(entity).take::<ObservedDependencies>() {
+            observed.clear(world);
+        }
     }
 
     fn attach_children(&self, world: &mut World, entity: Entity) -> bool {
-        if let Some(view_cell) = world.entity(entity).get::<ViewStateCell<V>>() {
+        if let Some(view_cell) = world.entity(entity).get::<ViewStateCell<V, Ctx>>() {
             let vs = view_cell.0.clone();
             let mut inner = vs.lock().unwrap();
             inner.attach_children(world)
@@ -181,7 +221,7 @@ impl<V: View> AnyViewAdapter for ViewAdapter<V> {
 }
 
 #[derive(Component)]
-pub struct ViewThunk(pub(crate) &'static dyn AnyViewAdapter);
+pub struct ViewThunk<Ctx: ViewContext = Cx>(pub(crate) &'static dyn AnyViewAdapter<Ctx>);
 
 /// An ECS component which holds a reference to the root of a view hierarchy.
 #[derive(Component)]
@@ -217,25 +257,69 @@ pub(crate) fn build_views(world: &mut World) {
     }
 }
 
+/// Ensure every view entity has a corresponding [`Aggregate`] node before anything tries to mark
+/// it dirty or query it, attaching new entities under their parent's aggregate (via
+/// [`AggregationTree::attach`]), or registering them as a fresh root aggregate if they have no
+/// parent or their parent never gets one (e.g. a non-view ancestor).
+fn register_aggregates(world: &mut World) {
+    let mut unregistered = world
+        .query_filtered::<Entity, (With<ViewThunk>, Without<Aggregate>)>()
+        .iter(world)
+        .collect::<Vec<_>>();
+
+    // An entity's parent may itself be unregistered this pass (e.g. a root and its first child
+    // spawned in the same frame), so retry until a full pass makes no further progress.
+    while !unregistered.is_empty() {
+        let mut remaining = Vec::new();
+        let mut progressed = false;
+        for entity in unregistered.drain(..) {
+            match world.entity(entity).get::<Parent>().map(Parent::get) {
+                Some(parent) if world.get::<Aggregate>(parent).is_some() => {
+                    AggregationTree::attach(world, entity, parent);
+                    progressed = true;
+                }
+                Some(_) => remaining.push(entity),
+                None => {
+                    world.entity_mut(entity).insert(Aggregate::new());
+                    progressed = true;
+                }
+            }
+        }
+        if !progressed {
+            // Remaining entities' parents will never get an `Aggregate` of their own; register
+            // them as roots so they aren't silently skipped every frame.
+            for entity in remaining {
+                world.entity_mut(entity).insert(Aggregate::new());
+            }
+            break;
+        }
+        unregistered = remaining;
+    }
+}
+
 pub(crate) fn rebuild_views(world: &mut World) {
     // let mut divergence_ct: usize = 0;
     // let mut prev_change_ct: usize = 0;
+    register_aggregates(world);
     let this_run = world.change_tick();
 
-    // let mut v = HashSet::new();
+    // Drain the set of scopes whose watched component dependencies were inserted/removed since
+    // the last run. These are populated by observers installed per-dependency (see
+    // `reactive_observers::ObservedDependencies`) rather than by scanning every `TrackingScope`.
+    let mut changed = world
+        .get_resource_or_insert_with(PendingRebuilds::default)
+        .drain();
 
-    // Scan changed resources
     let mut scopes = world.query::<(Entity, &mut TrackingScope, &ViewThunk)>();
-    let changed = scopes
-        .iter(world)
-        .filter_map(|(e, scope, _)| {
-            if scope.dependencies_changed(world, this_run) {
-                Some(e)
-            } else {
-                None
-            }
-        })
-        .collect::<Vec<_>>();
+
+    // Resources aren't entities, so they can't be watched with an entity observer the way
+    // component dependencies are above; resource dependencies are rechecked here instead. This
+    // loop visits every `TrackingScope`; a scope that reads no resource has nothing to check.
+    for (entity, scope, _) in scopes.iter(world) {
+        if scope.resource_deps_changed(world, this_run) && !changed.contains(&entity) {
+            changed.push(entity);
+        }
+    }
 
     // if !changed.is_empty() {
     //     println!("# Changed views: {:?}", changed.len());
@@ -266,6 +350,18 @@ pub(crate) fn rebuild_views(world: &mut World) {
             cleanup_fn(world);
         }
 
+        // Drop observers for dependencies read on a *previous* rebuild before this one re-tracks
+        // whatever it actually reads this time. `watch` only de-duplicates repeated reads within
+        // a rebuild; it has no way to notice a read that stopped happening, so without this a
+        // scope that stops reading a component would keep its stale observer triggering rebuilds
+        // forever.
+        if let Some(mut observed) = world
+            .entity_mut(*scope_entity)
+            .take::<ObservedDependencies>()
+        {
+            observed.clear(world);
+        }
+
         // Run the reaction
         let (_, _, view_cell) = scopes.get_mut(world, *scope_entity).unwrap();
         let mut next_scope = TrackingScope::new(this_run);
@@ -274,6 +370,7 @@ pub(crate) fn rebuild_views(world: &mut World) {
             #[cfg(feature = "verbose")]
             info!("View output changed: {}", *scope_entity);
             world.entity_mut(*scope_entity).insert(OutputChanged);
+            AggregationTree::mark_dirty(world, *scope_entity);
         }
 
         // Replace deps and cleanups in the current scope with the next scope.
@@ -331,18 +428,34 @@ pub(crate) fn rebuild_views(world: &mut World) {
 }
 
 pub(crate) fn reattach_children(world: &mut World) {
-    let mut changed_views = Vec::<Entity>::new();
+    register_aggregates(world);
+
+    // Real aggregate roots: entities whose `Aggregate` has no parent. Filtering on `ViewRoot`
+    // alone would miss `register_aggregates`'s fallback case, where a view becomes its own root
+    // aggregate because its ancestor chain never reaches a registered `Aggregate` (e.g. a
+    // non-view ancestor) — that subtree's dirty work would otherwise be invisible here.
+    let mut roots_query = world.query_filtered::<Entity, With<Aggregate>>();
+    let root_candidates = roots_query.iter(world).collect::<Vec<_>>();
+    let root_entities: Vec<Entity> = root_candidates
+        .into_iter()
+        .filter(|&e| world.get::<Aggregate>(e).unwrap().is_root())
+        .collect();
+
     let mut work_queue = HashSet::<Entity>::new();
-    let mut changed_views_query = world.query_filtered::<Entity, With<OutputChanged>>();
-    for view_entity in changed_views_query.iter(world) {
-        changed_views.push(view_entity);
-        if let Some(parent) = world.entity(view_entity).get::<Parent>() {
-            work_queue.insert(parent.get());
+    for root in root_entities {
+        if !AggregationTree::root_has_pending_work(world, root) {
+            continue;
+        }
+        // The root aggregate's `dirty_scopes` is already exactly this subtree's set of changed
+        // views (see `AggregationTree::dirty_scopes`), so read it directly instead of re-scanning
+        // every view entity in the world for an `OutputChanged` marker.
+        for view_entity in AggregationTree::dirty_scopes(world, root) {
+            world.entity_mut(view_entity).remove::<OutputChanged>();
+            AggregationTree::clear_dirty(world, view_entity);
+            if let Some(parent) = world.entity(view_entity).get::<Parent>() {
+                work_queue.insert(parent.get());
+            }
         }
-    }
-
-    for view_entity in changed_views.drain(..) {
-        world.entity_mut(view_entity).remove::<OutputChanged>();
     }
 
     while !work_queue.is_empty() {